@@ -10,20 +10,58 @@
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
 use oxc_allocator::Allocator;
 use oxc_codegen::{Codegen, CodegenOptions};
+use oxc_diagnostics::miette::Diagnostic as MietteDiagnostic;
+use oxc_diagnostics::{OxcDiagnostic, Severity as OxcSeverity};
 use oxc_parser::Parser;
 use oxc_semantic::SemanticBuilder;
+use oxc_sourcemap::SourceMap;
 use oxc_span::SourceType;
-use oxc_transformer::{TransformOptions, Transformer};
+use oxc_transformer::{JsxRuntime as OxcJsxRuntime, TransformOptions, Transformer};
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
 use parking_lot::RwLock;
 
+/// Severity of a [`Diagnostic`], mirroring Oxc/miette severities.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A structured compiler diagnostic carrying a source position, rather than a
+/// flattened message string.
+///
+/// Modelled on Deno's `Diagnostic`/`DiagnosticItem` with its `Location`
+/// (`filename:line:col`). Line and column are 1-based.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub filename: String,
+    pub line: usize,
+    pub column: usize,
+    pub code: Option<String>,
+    pub message: String,
+    pub severity: Severity,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{} - {}", self.filename, self.line, self.column, self.message)?;
+        if let Some(code) = &self.code {
+            write!(f, " [{}]", code)?;
+        }
+        Ok(())
+    }
+}
+
 /// Error type for TypeScript compilation
 #[derive(Debug)]
 pub enum CompileError {
+    /// One or more positioned diagnostics from parsing/transforming.
+    Diagnostics(Vec<Diagnostic>),
     ParseError(String),
     TransformError(String),
     CodegenError(String),
@@ -32,6 +70,10 @@ pub enum CompileError {
 impl std::fmt::Display for CompileError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            CompileError::Diagnostics(diags) => {
+                let rendered: Vec<String> = diags.iter().map(|d| d.to_string()).collect();
+                write!(f, "{}", rendered.join("\n"))
+            },
             CompileError::ParseError(msg) => write!(f, "TypeScript parse error: {}", msg),
             CompileError::TransformError(msg) => write!(f, "TypeScript transform error: {}", msg),
             CompileError::CodegenError(msg) => write!(f, "JavaScript codegen error: {}", msg),
@@ -41,6 +83,11 @@ impl std::fmt::Display for CompileError {
 
 impl std::error::Error for CompileError {}
 
+/// Compiler identity folded into every cache key: this crate's package version.
+/// Entries are invalidated when that version changes; an upgrade of the Oxc
+/// dependency alone does not change it.
+const COMPILER_VERSION: &str = concat!("oxc-transpiler/", env!("CARGO_PKG_VERSION"));
+
 /// Simple in-memory cache for compiled TypeScript
 /// Maps hash(source_code) -> compiled JavaScript
 fn get_cache() -> &'static RwLock<HashMap<u64, String>> {
@@ -48,6 +95,47 @@ fn get_cache() -> &'static RwLock<HashMap<u64, String>> {
     CACHE.get_or_init(|| RwLock::new(HashMap::new()))
 }
 
+/// Optional directory backing the on-disk cache layer. When unset, compilation
+/// is cached in memory only.
+fn disk_cache_dir() -> &'static RwLock<Option<PathBuf>> {
+    static DIR: OnceLock<RwLock<Option<PathBuf>>> = OnceLock::new();
+    DIR.get_or_init(|| RwLock::new(None))
+}
+
+/// Configure the directory used for the persistent compilation cache (like
+/// Deno's `DiskCache`). Pass `None` to disable disk caching and fall back to the
+/// in-memory map. The directory is created on first write.
+pub fn set_cache_dir(dir: Option<PathBuf>) {
+    *disk_cache_dir().write() = dir;
+}
+
+/// Path of the cached artifact for `key` with the given extension, if a disk
+/// cache directory is configured.
+fn disk_cache_path(key: u64, ext: &str) -> Option<PathBuf> {
+    disk_cache_dir()
+        .read()
+        .as_ref()
+        .map(|dir| dir.join(format!("{:016x}.{}", key, ext)))
+}
+
+/// Read a cached artifact from disk, if present.
+fn disk_read(key: u64, ext: &str) -> Option<String> {
+    let path = disk_cache_path(key, ext)?;
+    std::fs::read_to_string(path).ok()
+}
+
+/// Write a compiled artifact to disk, best-effort (cache failures are non-fatal).
+fn disk_write(key: u64, ext: &str, contents: &str) {
+    if let Some(path) = disk_cache_path(key, ext) {
+        if let Some(parent) = path.parent() {
+            let _ = std::fs::create_dir_all(parent);
+        }
+        if let Err(e) = std::fs::write(&path, contents) {
+            log::warn!("TypeScript: failed to write disk cache {}: {}", path.display(), e);
+        }
+    }
+}
+
 /// Compile TypeScript source code to JavaScript
 ///
 /// # Arguments
@@ -59,53 +147,338 @@ fn get_cache() -> &'static RwLock<HashMap<u64, String>> {
 pub fn compile_typescript_to_js(source: &str, filename: &str) -> Result<String, CompileError> {
     log::info!("TypeScript: Compiling {} ({} bytes)", filename, source.len());
 
-    // Check cache first
-    let cache_key = calculate_hash(source);
+    // Cache key folds in the source, the resolved source type and the compiler
+    // version so upgrades invalidate stale entries.
+    let source_type = resolve_source_type(filename);
+    let cache_key = calculate_hash_keyed(source, source_type);
+
+    // Fast path: in-memory cache.
     {
         let cache = get_cache().read();
         if let Some(cached) = cache.get(&cache_key) {
-            log::info!("TypeScript: Cache hit for {}", filename);
+            log::info!("TypeScript: Cache hit (memory) for {}", filename);
             return Ok(cached.clone());
         }
     }
 
+    // Next: the persistent on-disk layer, if configured. A disk hit is promoted
+    // back into memory.
+    if let Some(cached) = disk_read(cache_key, "js") {
+        log::info!("TypeScript: Cache hit (disk) for {}", filename);
+        store_in_memory(cache_key, &cached);
+        return Ok(cached);
+    }
+
     // Compile TypeScript to JavaScript
     let compiled = compile_typescript_internal(source, filename)?;
     log::info!("TypeScript: Successfully compiled {} to {} bytes of JS", filename, compiled.len());
 
-    // Store in cache
+    store_in_memory(cache_key, &compiled);
+    disk_write(cache_key, "js", &compiled);
+
+    Ok(compiled)
+}
+
+/// Compile TypeScript from raw bytes, transcoding to UTF-8 first.
+///
+/// [`compile_typescript_to_js`] takes `&str` and so assumes the source is
+/// already valid UTF-8. Sources served as UTF-16 (with a BOM) or in a legacy
+/// single-byte encoding never reach the parser through that path. This entry
+/// point sniffs a UTF-8/UTF-16LE/UTF-16BE byte-order mark, falls back to an
+/// explicit `charset` hint (e.g. parsed from an HTTP `Content-Type` header) and
+/// otherwise assumes UTF-8, transcoding to UTF-8 before handing off to the
+/// normal compiler — the same non-UTF8 source handling Deno grew for `deno run`.
+///
+/// The BOM is stripped during decoding, so identical content in different
+/// encodings hashes to the same cache key.
+pub fn compile_typescript_bytes(
+    bytes: &[u8],
+    filename: &str,
+    charset: Option<&str>,
+) -> Result<String, CompileError> {
+    let source = decode_to_utf8(bytes, charset)?;
+    compile_typescript_to_js(&source, filename)
+}
+
+/// Decode `bytes` to a BOM-free UTF-8 `String`.
+///
+/// A leading byte-order mark wins over any `charset` hint, mirroring how the
+/// Encoding Standard's BOM sniff overrides a declared label. When no BOM is
+/// present the `charset` label (if any) selects the encoding; an unrecognized
+/// label is an error rather than a silent fallback.
+fn decode_to_utf8(bytes: &[u8], charset: Option<&str>) -> Result<String, CompileError> {
+    // BOM sniff takes precedence over the declared charset.
+    let (encoding, bom_len) = match bytes {
+        [0xEF, 0xBB, 0xBF, ..] => (UTF_8, 3),
+        [0xFF, 0xFE, ..] => (UTF_16LE, 2),
+        [0xFE, 0xFF, ..] => (UTF_16BE, 2),
+        _ => match charset {
+            Some(label) => {
+                let encoding = Encoding::for_label(label.trim().as_bytes()).ok_or_else(|| {
+                    CompileError::ParseError(format!("unknown source charset {:?}", label))
+                })?;
+                (encoding, 0)
+            },
+            None => (UTF_8, 0),
+        },
+    };
+
+    // Strip the BOM before decoding so it does not perturb the content or the
+    // downstream cache key.
+    let (decoded, _, _) = encoding.decode(&bytes[bom_len..]);
+    Ok(decoded.into_owned())
+}
+
+/// Insert a compiled artifact into the in-memory cache, enforcing the size cap.
+fn store_in_memory(key: u64, compiled: &str) {
+    let mut cache = get_cache().write();
+    // Limit cache size to 1000 entries
+    if cache.len() > 1000 {
+        cache.clear();
+    }
+    cache.insert(key, compiled.to_string());
+}
+
+/// JSX transform runtime, mirroring TypeScript's `jsx` compiler option.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum JsxRuntime {
+    /// Classic runtime (`React.createElement`).
+    Classic,
+    /// Automatic runtime (`jsx`/`jsxs` from the import source).
+    Automatic,
+}
+
+/// Transform configuration resolvable from a `tsconfig.json` `compilerOptions`
+/// block, used to drive Oxc's [`TransformOptions`].
+///
+/// Follows the way Deno's `EmitTranspileOptions` adjusts transpilation from the
+/// loaded tsconfig. The struct is hashed into the compilation cache key so that
+/// changing any option invalidates stale artifacts.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CompileOptions {
+    /// JSX runtime (classic vs automatic).
+    pub jsx_runtime: JsxRuntime,
+    /// Module specifier providing the JSX runtime (`jsxImportSource`).
+    pub jsx_import_source: Option<String>,
+    /// Use TypeScript's legacy/experimental decorator semantics.
+    pub legacy_decorators: bool,
+    /// Target for syntax downleveling (e.g. `"es2015"`); `None` keeps syntax.
+    pub target: Option<String>,
+    /// Honor `verbatimModuleSyntax`: do not elide imports that are referenced
+    /// only in type positions, keeping them verbatim (explicit `import type`
+    /// declarations are still removed, as Oxc cannot preserve those).
+    pub verbatim_module_syntax: bool,
+}
+
+impl Default for CompileOptions {
+    fn default() -> Self {
+        CompileOptions {
+            jsx_runtime: JsxRuntime::Automatic,
+            jsx_import_source: None,
+            legacy_decorators: false,
+            target: None,
+            verbatim_module_syntax: false,
+        }
+    }
+}
+
+impl CompileOptions {
+    /// Resolve options from a `tsconfig.json` document, reading its
+    /// `compilerOptions` block. Unknown or absent keys keep their defaults.
+    pub fn from_tsconfig(tsconfig_json: &str) -> Result<Self, CompileError> {
+        let value: serde_json::Value = serde_json::from_str(tsconfig_json)
+            .map_err(|e| CompileError::ParseError(format!("tsconfig.json: {}", e)))?;
+        let opts = value.get("compilerOptions").unwrap_or(&serde_json::Value::Null);
+
+        let mut resolved = CompileOptions::default();
+        match opts.get("jsx").and_then(|v| v.as_str()) {
+            Some("react") => resolved.jsx_runtime = JsxRuntime::Classic,
+            Some("react-jsx") | Some("react-jsxdev") => {
+                resolved.jsx_runtime = JsxRuntime::Automatic
+            },
+            // `preserve` means emit JSX untransformed, which Oxc cannot do — it
+            // always lowers JSX. Warn instead of silently aliasing to the classic
+            // runtime, and fall back to the classic lowering as the closest emit.
+            Some("preserve") | Some("react-native") => {
+                log::warn!(
+                    "TypeScript: tsconfig jsx={:?} cannot be preserved by Oxc; JSX will be \
+                     transformed with the classic runtime",
+                    opts.get("jsx").and_then(|v| v.as_str()).unwrap_or_default(),
+                );
+                resolved.jsx_runtime = JsxRuntime::Classic;
+            },
+            _ => {},
+        }
+        if let Some(src) = opts.get("jsxImportSource").and_then(|v| v.as_str()) {
+            resolved.jsx_import_source = Some(src.to_string());
+        }
+        if let Some(flag) = opts.get("experimentalDecorators").and_then(|v| v.as_bool()) {
+            resolved.legacy_decorators = flag;
+        }
+        if let Some(target) = opts.get("target").and_then(|v| v.as_str()) {
+            resolved.target = Some(target.to_ascii_lowercase());
+        }
+        if let Some(flag) = opts.get("verbatimModuleSyntax").and_then(|v| v.as_bool()) {
+            resolved.verbatim_module_syntax = flag;
+        }
+        Ok(resolved)
+    }
+}
+
+/// Compile TypeScript to JavaScript using explicit transform options.
+///
+/// Like [`compile_typescript_to_js`] but configured from a [`CompileOptions`]
+/// (typically resolved from a `tsconfig.json`). The options are folded into the
+/// cache key so differently-configured compilations of the same source do not
+/// alias.
+pub fn compile_typescript_to_js_with_options(
+    source: &str,
+    filename: &str,
+    options: &CompileOptions,
+) -> Result<String, CompileError> {
+    log::info!("TypeScript: Compiling {} with options {:?}", filename, options);
+
+    let source_type = resolve_source_type(filename);
+    let cache_key = calculate_hash_keyed_with_options(source, source_type, options);
+
     {
-        let mut cache = get_cache().write();
-        // Limit cache size to 1000 entries
-        if cache.len() > 1000 {
-            cache.clear();
+        let cache = get_cache().read();
+        if let Some(cached) = cache.get(&cache_key) {
+            log::info!("TypeScript: Cache hit (memory) for {}", filename);
+            return Ok(cached.clone());
         }
-        cache.insert(cache_key, compiled.clone());
+    }
+    if let Some(cached) = disk_read(cache_key, "js") {
+        log::info!("TypeScript: Cache hit (disk) for {}", filename);
+        store_in_memory(cache_key, &cached);
+        return Ok(cached);
     }
 
+    let (compiled, _) = compile_typescript_internal_with_map(source, filename, false, options)?;
+    store_in_memory(cache_key, &compiled);
+    disk_write(cache_key, "js", &compiled);
     Ok(compiled)
 }
 
+/// Where to place the generated source map.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum SourceMapMode {
+    /// Return the map as a standalone `.js.map` JSON string.
+    External,
+    /// Append an inline `//# sourceMappingURL=data:...;base64,...` comment to the
+    /// generated code.
+    Inline,
+}
+
+/// Compile TypeScript to JavaScript, also producing a source map.
+///
+/// Mirrors Deno's `transpile()`, which returns `(code, Option<map>)`. In
+/// [`SourceMapMode::External`] the returned map is the JSON text of a
+/// standalone `.js.map`; in [`SourceMapMode::Inline`] the map is folded into
+/// the code as a base64 `sourceMappingURL` comment and the returned map is
+/// `None`.
+pub fn compile_typescript_to_js_with_map(
+    source: &str,
+    filename: &str,
+    mode: SourceMapMode,
+) -> Result<(String, Option<String>), CompileError> {
+    log::info!("TypeScript: Compiling {} with source map ({} bytes)", filename, source.len());
+
+    // The emitted code differs between modes (inline folds the map in), so the
+    // map mode is folded into the cache key. For external maps the `.map`
+    // artifact is cached alongside the `.js`, matching Deno's on-disk emit.
+    let source_type = resolve_source_type(filename);
+    let cache_key = calculate_hash_keyed_with_map_mode(source, source_type, mode);
+
+    {
+        let cache = get_cache().read();
+        if let Some(code) = cache.get(&cache_key) {
+            match mode {
+                SourceMapMode::Inline => {
+                    log::info!("TypeScript: Cache hit (memory) for {}", filename);
+                    return Ok((code.clone(), None));
+                },
+                SourceMapMode::External => {
+                    if let Some(map) = disk_read(cache_key, "map") {
+                        log::info!("TypeScript: Cache hit (memory+disk) for {}", filename);
+                        return Ok((code.clone(), Some(map)));
+                    }
+                },
+            }
+        }
+    }
+    if let Some(code) = disk_read(cache_key, "js") {
+        match mode {
+            SourceMapMode::Inline => {
+                log::info!("TypeScript: Cache hit (disk) for {}", filename);
+                store_in_memory(cache_key, &code);
+                return Ok((code, None));
+            },
+            SourceMapMode::External => {
+                if let Some(map) = disk_read(cache_key, "map") {
+                    log::info!("TypeScript: Cache hit (disk) for {}", filename);
+                    store_in_memory(cache_key, &code);
+                    return Ok((code, Some(map)));
+                }
+            },
+        }
+    }
+
+    let (code, map) =
+        compile_typescript_internal_with_map(source, filename, true, &CompileOptions::default())?;
+    let map = map.ok_or_else(|| {
+        CompileError::CodegenError("codegen did not produce a source map".to_string())
+    })?;
+
+    match mode {
+        SourceMapMode::External => {
+            let map_json = map.to_json_string();
+            store_in_memory(cache_key, &code);
+            disk_write(cache_key, "js", &code);
+            disk_write(cache_key, "map", &map_json);
+            Ok((code, Some(map_json)))
+        },
+        SourceMapMode::Inline => {
+            let mut code = code;
+            code.push_str("\n//# sourceMappingURL=");
+            code.push_str(&map.to_data_url());
+            code.push('\n');
+            store_in_memory(cache_key, &code);
+            disk_write(cache_key, "js", &code);
+            Ok((code, None))
+        },
+    }
+}
+
 /// Internal compilation function using Oxc
 fn compile_typescript_internal(source: &str, filename: &str) -> Result<String, CompileError> {
+    compile_typescript_internal_with_map(source, filename, false, &CompileOptions::default())
+        .map(|(code, _)| code)
+}
+
+/// Shared compilation routine that optionally emits a source map.
+fn compile_typescript_internal_with_map(
+    source: &str,
+    filename: &str,
+    want_map: bool,
+    options: &CompileOptions,
+) -> Result<(String, Option<SourceMap>), CompileError> {
     // Create allocator for Oxc
     let allocator = Allocator::default();
 
     // Determine source type (TypeScript or TSX)
-    let source_type = SourceType::from_path(filename)
-        .unwrap_or_else(|_| SourceType::default().with_typescript(true));
+    let source_type = resolve_source_type(filename);
 
     // Parse the TypeScript code
     let parser_ret = Parser::new(&allocator, source, source_type).parse();
 
     // Check for parse errors
     if !parser_ret.errors.is_empty() {
-        let error_msgs: Vec<String> = parser_ret
-            .errors
-            .iter()
-            .map(|e| format!("{}", e))
-            .collect();
-        return Err(CompileError::ParseError(error_msgs.join("; ")));
+        return Err(CompileError::Diagnostics(to_diagnostics(
+            &parser_ret.errors,
+            source,
+            filename,
+        )));
     }
 
     let mut program = parser_ret.program;
@@ -114,8 +487,8 @@ fn compile_typescript_internal(source: &str, filename: &str) -> Result<String, C
     let semantic_ret = SemanticBuilder::new()
         .build(&program);
 
-    // Configure transform options to strip TypeScript
-    let transform_options = TransformOptions::default();
+    // Configure transform options from the caller's CompileOptions.
+    let transform_options = build_transform_options(options)?;
 
     // Apply TypeScript stripping transform
     let path = Path::new(filename);
@@ -123,25 +496,143 @@ fn compile_typescript_internal(source: &str, filename: &str) -> Result<String, C
         .build_with_scoping(semantic_ret.semantic.into_scoping(), &mut program);
 
     if !transform_result.errors.is_empty() {
-        let error_msgs: Vec<String> = transform_result
-            .errors
-            .iter()
-            .map(|e| format!("{}", e))
-            .collect();
-        return Err(CompileError::TransformError(error_msgs.join("; ")));
+        return Err(CompileError::Diagnostics(to_diagnostics(
+            &transform_result.errors,
+            source,
+            filename,
+        )));
     }
 
-    // Generate JavaScript code
-    let codegen_options = CodegenOptions::default();
+    // Generate JavaScript code, enabling source-map output when requested.
+    let codegen_options = CodegenOptions {
+        source_map_path: want_map.then(|| PathBuf::from(filename)),
+        ..CodegenOptions::default()
+    };
     let codegen_result = Codegen::new().with_options(codegen_options).build(&program);
 
-    Ok(codegen_result.code)
+    Ok((codegen_result.code, codegen_result.map))
+}
+
+/// Translate a [`CompileOptions`] into Oxc's [`TransformOptions`], applying the
+/// JSX runtime/import source, decorator semantics, target downleveling and
+/// `verbatimModuleSyntax` import elision.
+fn build_transform_options(options: &CompileOptions) -> Result<TransformOptions, CompileError> {
+    // Start from the target (if any) so syntax downleveling plugins are enabled.
+    let mut transform = match &options.target {
+        Some(target) => target.parse().map(TransformOptions::from).map_err(|e| {
+            CompileError::TransformError(format!("unsupported target {:?}: {}", target, e))
+        })?,
+        None => TransformOptions::default(),
+    };
+
+    transform.jsx.runtime = match options.jsx_runtime {
+        JsxRuntime::Classic => OxcJsxRuntime::Classic,
+        JsxRuntime::Automatic => OxcJsxRuntime::Automatic,
+    };
+    if let Some(source) = &options.jsx_import_source {
+        transform.jsx.import_source = Some(source.clone());
+    }
+    transform.decorators.legacy = options.legacy_decorators;
+    // verbatimModuleSyntax: disable import elision so value imports used only in
+    // type positions are kept verbatim; only explicit `import type` is removed.
+    transform.typescript.only_remove_type_imports = options.verbatim_module_syntax;
+
+    Ok(transform)
+}
+
+/// Convert Oxc diagnostics into positioned [`Diagnostic`]s, mapping each span
+/// offset to a 1-based line/column in `source`.
+fn to_diagnostics(errors: &[OxcDiagnostic], source: &str, filename: &str) -> Vec<Diagnostic> {
+    errors
+        .iter()
+        .map(|error| {
+            // Use the first label's span (if any) as the primary location.
+            let offset = error
+                .labels()
+                .and_then(|mut labels| labels.next())
+                .map(|label| label.offset());
+            let (line, column) = offset
+                .map(|offset| offset_to_line_col(source, offset))
+                .unwrap_or((1, 1));
+            let severity = match error.severity() {
+                Some(OxcSeverity::Warning) => Severity::Warning,
+                Some(OxcSeverity::Advice) => Severity::Warning,
+                _ => Severity::Error,
+            };
+            let code = error.code().map(|code| code.to_string()).filter(|c| !c.is_empty());
+            Diagnostic {
+                filename: filename.to_string(),
+                line,
+                column,
+                code,
+                message: error.to_string(),
+                severity,
+            }
+        })
+        .collect()
+}
+
+/// Map a byte offset into `source` to a 1-based (line, column) position.
+fn offset_to_line_col(source: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(source.len());
+    let mut line = 1;
+    let mut column = 1;
+    for (i, ch) in source.char_indices() {
+        if i >= offset {
+            break;
+        }
+        if ch == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    (line, column)
+}
+
+/// Resolve the [`SourceType`] for a file, defaulting to TypeScript.
+fn resolve_source_type(filename: &str) -> SourceType {
+    SourceType::from_path(filename)
+        .unwrap_or_else(|_| SourceType::default().with_typescript(true))
+}
+
+/// Calculate a cache key from the source, its source type and the compiler
+/// version, so entries are invalidated across file kinds and compiler
+/// upgrades. The key is computed from decoded text, so identical content read
+/// from different encodings shares an entry.
+fn calculate_hash_keyed(source: &str, source_type: SourceType) -> u64 {
+    calculate_hash_keyed_with_options(source, source_type, &CompileOptions::default())
 }
 
-/// Calculate hash for caching
-fn calculate_hash(source: &str) -> u64 {
+/// As [`calculate_hash_keyed`], additionally folding in the transform options so
+/// differently-configured compilations do not share a cache entry.
+fn calculate_hash_keyed_with_options(
+    source: &str,
+    source_type: SourceType,
+    options: &CompileOptions,
+) -> u64 {
     let mut hasher = DefaultHasher::new();
     source.hash(&mut hasher);
+    // SourceType is not Hash; fold in the distinguishing flags explicitly.
+    source_type.is_typescript().hash(&mut hasher);
+    source_type.is_jsx().hash(&mut hasher);
+    source_type.is_module().hash(&mut hasher);
+    options.hash(&mut hasher);
+    COMPILER_VERSION.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// As [`calculate_hash_keyed`], additionally folding in the source-map mode so
+/// inline and external emits of the same source do not share a cache entry.
+fn calculate_hash_keyed_with_map_mode(
+    source: &str,
+    source_type: SourceType,
+    mode: SourceMapMode,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    calculate_hash_keyed(source, source_type).hash(&mut hasher);
+    mode.hash(&mut hasher);
     hasher.finish()
 }
 
@@ -203,6 +694,166 @@ mod tests {
         assert_eq!(result1.unwrap(), result2.unwrap());
     }
 
+    #[test]
+    fn test_source_map_external() {
+        let source = "const x: number = 1;\n";
+        let (code, map) =
+            compile_typescript_to_js_with_map(source, "test.ts", SourceMapMode::External).unwrap();
+        assert!(!code.contains("sourceMappingURL"));
+        let map = map.expect("external mode returns a map");
+        assert!(map.contains("\"mappings\""));
+    }
+
+    #[test]
+    fn test_source_map_inline() {
+        let source = "const x: number = 1;\n";
+        let (code, map) =
+            compile_typescript_to_js_with_map(source, "test.ts", SourceMapMode::Inline).unwrap();
+        assert!(map.is_none());
+        assert!(code.contains("//# sourceMappingURL=data:application/json;base64,"));
+    }
+
+    #[test]
+    fn test_source_map_external_cached_to_disk() {
+        let dir = std::env::temp_dir().join("servo_ts_map_cache_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        set_cache_dir(Some(dir.clone()));
+        clear_cache();
+
+        let source = "const m: number = 5;\n";
+        let (code1, map1) =
+            compile_typescript_to_js_with_map(source, "map.ts", SourceMapMode::External).unwrap();
+
+        // Both the code and the map must have been persisted to disk.
+        let key = calculate_hash_keyed_with_map_mode(
+            source,
+            resolve_source_type("map.ts"),
+            SourceMapMode::External,
+        );
+        assert!(disk_cache_path(key, "js").unwrap().exists());
+        assert!(disk_cache_path(key, "map").unwrap().exists());
+
+        // Drop the in-memory layer; the disk layer should still serve both.
+        clear_cache();
+        let (code2, map2) =
+            compile_typescript_to_js_with_map(source, "map.ts", SourceMapMode::External).unwrap();
+        assert_eq!(code1, code2);
+        assert_eq!(map1, map2);
+
+        set_cache_dir(None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_compile_options_from_tsconfig() {
+        let tsconfig = r#"{
+            "compilerOptions": {
+                "jsx": "react",
+                "jsxImportSource": "preact",
+                "experimentalDecorators": true,
+                "target": "es2015",
+                "verbatimModuleSyntax": true
+            }
+        }"#;
+        let opts = CompileOptions::from_tsconfig(tsconfig).unwrap();
+        assert_eq!(opts.jsx_runtime, JsxRuntime::Classic);
+        assert_eq!(opts.jsx_import_source.as_deref(), Some("preact"));
+        assert!(opts.legacy_decorators);
+        assert_eq!(opts.target.as_deref(), Some("es2015"));
+        assert!(opts.verbatim_module_syntax);
+    }
+
+    #[test]
+    fn test_verbatim_module_syntax_keeps_type_only_import() {
+        // `Foo` is referenced only in a type position, so the default transform
+        // elides the import; verbatimModuleSyntax keeps it verbatim.
+        let source = "import { Foo } from \"./foo\";\nlet x: Foo;\nexport { x };\n";
+
+        let elided = CompileOptions::default();
+        let js = compile_typescript_to_js_with_options(source, "v.ts", &elided).unwrap();
+        assert!(!js.contains("./foo"), "type-only import should be elided by default");
+
+        let verbatim = CompileOptions { verbatim_module_syntax: true, ..Default::default() };
+        let js = compile_typescript_to_js_with_options(source, "v.ts", &verbatim).unwrap();
+        assert!(js.contains("./foo"), "verbatimModuleSyntax should keep the import verbatim");
+    }
+
+    #[test]
+    fn test_options_affect_cache_key() {
+        let source = "const x = 1;";
+        let st = resolve_source_type("a.ts");
+        let classic = CompileOptions { jsx_runtime: JsxRuntime::Classic, ..Default::default() };
+        let automatic =
+            CompileOptions { jsx_runtime: JsxRuntime::Automatic, ..Default::default() };
+        assert_ne!(
+            calculate_hash_keyed_with_options(source, st, &classic),
+            calculate_hash_keyed_with_options(source, st, &automatic),
+        );
+    }
+
+    #[test]
+    fn test_disk_cache_survives_memory_clear() {
+        let dir = std::env::temp_dir().join("servo_ts_cache_test");
+        let _ = std::fs::remove_dir_all(&dir);
+        set_cache_dir(Some(dir.clone()));
+        clear_cache();
+
+        let source = "const n: number = 7;\n";
+        let first = compile_typescript_to_js(source, "disk.ts").unwrap();
+
+        // The artifact must have been persisted to disk.
+        let key = calculate_hash_keyed(source, resolve_source_type("disk.ts"));
+        assert!(disk_cache_path(key, "js").unwrap().exists());
+
+        // Drop the in-memory layer; the disk layer should still serve the hit.
+        clear_cache();
+        let second = compile_typescript_to_js(source, "disk.ts").unwrap();
+        assert_eq!(first, second);
+
+        set_cache_dir(None);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_bytes_utf16le_bom() {
+        // `const x: number = 1;\n` encoded as UTF-16LE with a BOM.
+        let text = "const x: number = 1;\n";
+        let mut bytes = vec![0xFF, 0xFE];
+        for unit in text.encode_utf16() {
+            bytes.extend_from_slice(&unit.to_le_bytes());
+        }
+        let js = compile_typescript_bytes(&bytes, "u16.ts", None).unwrap();
+        assert!(!js.contains(": number"));
+    }
+
+    #[test]
+    fn test_bytes_bom_strip_stable_cache_key() {
+        // The same content, once as plain UTF-8 and once as UTF-8 with a BOM,
+        // must produce identical output via the shared (BOM-free) cache key.
+        clear_cache();
+        let text = "const y: number = 2;\n";
+        let plain = compile_typescript_bytes(text.as_bytes(), "bom.ts", None).unwrap();
+
+        let mut with_bom = vec![0xEF, 0xBB, 0xBF];
+        with_bom.extend_from_slice(text.as_bytes());
+        let bommed = compile_typescript_bytes(&with_bom, "bom.ts", None).unwrap();
+        assert_eq!(plain, bommed);
+    }
+
+    #[test]
+    fn test_bytes_charset_hint() {
+        // windows-1252 is ASCII-compatible for this source; the hint selects it.
+        let text = "const z: number = 3;\n";
+        let js = compile_typescript_bytes(text.as_bytes(), "w.ts", Some("windows-1252")).unwrap();
+        assert!(!js.contains(": number"));
+    }
+
+    #[test]
+    fn test_bytes_unknown_charset_errors() {
+        let err = compile_typescript_bytes(b"const a = 1;", "x.ts", Some("not-a-charset"));
+        assert!(matches!(err, Err(CompileError::ParseError(_))));
+    }
+
     #[test]
     fn test_invalid_typescript() {
         let source = "const x: = 42;"; // Invalid syntax
@@ -210,4 +861,22 @@ mod tests {
         let result = compile_typescript_to_js(source, "test.ts");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_structured_diagnostics_have_positions() {
+        // Error on the second line; diagnostics should report line 2.
+        let source = "const ok = 1;\nconst bad: = 2;\n";
+        let err = compile_typescript_to_js(source, "bad.ts").unwrap_err();
+        match err {
+            CompileError::Diagnostics(diags) => {
+                assert!(!diags.is_empty());
+                let first = &diags[0];
+                assert_eq!(first.filename, "bad.ts");
+                assert_eq!(first.line, 2);
+                // Display renders the familiar file:line:col - message form.
+                assert!(first.to_string().starts_with("bad.ts:2:"));
+            },
+            other => panic!("expected structured diagnostics, got {:?}", other),
+        }
+    }
 }